@@ -0,0 +1,324 @@
+use nmea::{parse_str, GgaData, GsaData, GsvData, ParseResult, RmcData};
+use reqwest::Client;
+use serde::Deserialize;
+
+/// A single fix plus whatever ancillary quality data the source exposed,
+/// normalized so `update_metrics` doesn't need to know whether it came from
+/// the device's XML dialect or a raw NMEA 0183 stream.
+#[derive(Debug, Default)]
+pub struct Gnss {
+    pub ant: Option<String>,
+    pub svs_used: Vec<(String, i64)>,
+    pub svs_seen: Vec<(String, i64)>,
+    pub satellites: Vec<Satellite>,
+
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub alt: Option<f64>,
+
+    pub fix_quality: Option<i64>,
+    pub hdop: Option<f64>,
+    pub vdop: Option<f64>,
+    pub pdop: Option<f64>,
+
+    /// Current GPS-UTC leap second count. Neither XML nor NMEA sentences
+    /// carry this directly, so it's usually left `None` here and filled in
+    /// from the configured default leap-second value instead.
+    pub leap_seconds: Option<i64>,
+    pub leap_second_planned: Option<bool>,
+    /// GPS time minus UTC time, in nanoseconds. Derived from `leap_seconds`.
+    pub gps_offset_ns: Option<i64>,
+    /// The receiver's own clock minus UTC, in nanoseconds. Neither the XML
+    /// dialect nor NMEA sentences expose an actual clock-discipline
+    /// measurement, so this is filled in as `0` once a fix is obtained
+    /// (receiver timestamps are treated as UTC) rather than left unpublished;
+    /// it is not a true holdover/drift figure.
+    pub utc_offset_ns: Option<i64>,
+}
+
+/// Per-satellite signal detail, as reported by NMEA `GSV` sentences (or the
+/// XML dialect's equivalent, where the device exposes it).
+#[derive(Debug)]
+pub struct Satellite {
+    pub constellation: String,
+    pub prn: String,
+    pub elevation: Option<f64>,
+    pub azimuth: Option<f64>,
+    pub cn0: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlGnss {
+    ant: String,
+    // r#const: String,
+    // svused: i64,
+    gpsinfo: String,
+    bdinfo: String,
+    glinfo: String,
+    lat: String,
+    long: String,
+    alt: String,
+
+    #[serde(default, rename = "svs")]
+    svs: Option<XmlSatellites>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlSatellites {
+    #[serde(default, rename = "sv")]
+    sv: Vec<XmlSatellite>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlSatellite {
+    #[serde(rename = "const")]
+    constellation: String,
+    prn: String,
+    cn0: Option<f64>,
+    el: Option<f64>,
+    az: Option<f64>,
+}
+
+impl From<XmlGnss> for Gnss {
+    fn from(xml: XmlGnss) -> Self {
+        let satellites = xml
+            .svs
+            .map(|svs| {
+                svs.sv
+                    .into_iter()
+                    .map(|sv| Satellite {
+                        constellation: sv.constellation,
+                        prn: sv.prn,
+                        elevation: sv.el,
+                        azimuth: sv.az,
+                        cn0: sv.cn0,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Gnss {
+            ant: Some(xml.ant),
+            svs_used: vec![],
+            svs_seen: vec![],
+            satellites,
+            lat: parse_lat_long(&xml.lat).ok(),
+            lon: parse_lat_long(&xml.long).ok(),
+            alt: xml.alt.trim_end_matches(" m").parse().ok(),
+            fix_quality: None,
+            hdop: None,
+            vdop: None,
+            pdop: None,
+            leap_seconds: None,
+            leap_second_planned: None,
+            gps_offset_ns: None,
+            utc_offset_ns: None,
+        }
+        .with_used_seen(&xml.gpsinfo, &xml.bdinfo, &xml.glinfo)
+    }
+}
+
+impl Gnss {
+    fn with_used_seen(mut self, gpsinfo: &str, bdinfo: &str, glinfo: &str) -> Self {
+        if let Ok((used, seen)) = parse_used_seen(gpsinfo) {
+            self.svs_used.push(("GPS".into(), used));
+            self.svs_seen.push(("GPS".into(), seen));
+        }
+        if let Ok((used, seen)) = parse_used_seen(bdinfo) {
+            self.svs_used.push(("BeiDou".into(), used));
+            self.svs_seen.push(("BeiDou".into(), seen));
+        }
+        if let Ok((used, seen)) = parse_used_seen(glinfo) {
+            self.svs_used.push(("GLONASS".into(), used));
+            self.svs_seen.push(("GLONASS".into(), seen));
+        }
+        self
+    }
+}
+
+pub async fn read_gnss(target: &str, auth: Option<(&str, &str)>, client: &Client) -> anyhow::Result<Gnss> {
+    let mut builder = client.get(target);
+
+    if let Some((username, password)) = auth {
+        builder = builder.basic_auth(username, Some(password));
+    }
+
+    let xml = builder.send().await?.text().await?;
+    log::debug!("received XML body from {target}: {xml}");
+
+    let gnss = serde_xml_rs::from_str::<XmlGnss>(&xml)?;
+    log::debug!("parsed XML GNSS from {target}: {gnss:?}");
+
+    Ok(gnss.into())
+}
+
+/// Fetches a raw NMEA 0183 sentence stream and folds `GGA`, `GSA`, and `RMC`
+/// sentences into a single [`Gnss`] fix. Sentences are processed in whatever
+/// order the device emits them, with later sentences overriding fields
+/// earlier ones already set (mirrors a receiver emitting a fresh fix each
+/// cycle, one sentence per line).
+pub async fn read_gnss_nmea(target: &str, auth: Option<(&str, &str)>, client: &Client) -> anyhow::Result<Gnss> {
+    let mut builder = client.get(target);
+
+    if let Some((username, password)) = auth {
+        builder = builder.basic_auth(username, Some(password));
+    }
+
+    let stream = builder.send().await?.text().await?;
+    log::debug!("received NMEA stream from {target}: {stream}");
+
+    let mut gnss = Gnss::default();
+
+    for line in stream.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match parse_str(line) {
+            Ok(ParseResult::GGA(gga)) => apply_gga(&mut gnss, gga),
+            Ok(ParseResult::GSA(gsa)) => apply_gsa(&mut gnss, gsa),
+            Ok(ParseResult::GSV(gsv)) => apply_gsv(&mut gnss, gsv),
+            Ok(ParseResult::RMC(rmc)) => apply_rmc(&mut gnss, rmc),
+            Ok(_) => {}
+            Err(e) => log::warn!("failed to parse NMEA sentence {line:?}: {e}"),
+        }
+    }
+
+    Ok(gnss)
+}
+
+fn apply_gga(gnss: &mut Gnss, gga: GgaData) {
+    gnss.lat = gga.latitude.or(gnss.lat);
+    gnss.lon = gga.longitude.or(gnss.lon);
+    gnss.alt = gga.altitude.map(f64::from).or(gnss.alt);
+    gnss.fix_quality = gga.fix_type.map(|t| t as i64).or(gnss.fix_quality);
+
+    if let Some(used) = gga.fix_satellites {
+        gnss.svs_used = vec![("GNSS".into(), used as i64)];
+    }
+}
+
+/// `RMC` also carries position, plus an overall A/V validity flag. An
+/// invalid fix is commonly still transmitted (with stale or zeroed
+/// coordinates), so a void RMC is ignored rather than clobbering a good
+/// position already pulled from `GGA`.
+fn apply_rmc(gnss: &mut Gnss, rmc: RmcData) {
+    if !matches!(rmc.status_of_fix, nmea::RmcStatusOfFix::Autonomous | nmea::RmcStatusOfFix::Differential) {
+        return;
+    }
+
+    gnss.lat = rmc.lat.or(gnss.lat);
+    gnss.lon = rmc.lon.or(gnss.lon);
+}
+
+fn apply_gsa(gnss: &mut Gnss, gsa: GsaData) {
+    gnss.pdop = gsa.pdop.map(f64::from).or(gnss.pdop);
+    gnss.hdop = gsa.hdop.map(f64::from).or(gnss.hdop);
+    gnss.vdop = gsa.vdop.map(f64::from).or(gnss.vdop);
+}
+
+/// A `GSV` sentence only ever carries up to four satellites, with the
+/// receiver splitting a constellation's full view across several sentences
+/// (`number_of_sentences`/`sentence_num`). Since a whole stream fetch covers
+/// a full cycle, we just append every sentence's satellites as we see them;
+/// by the time the last sentence of the group arrives, `gnss.satellites`
+/// holds the complete view for that constellation.
+fn apply_gsv(gnss: &mut Gnss, gsv: GsvData) {
+    let constellation = constellation_name(gsv.gnss_type);
+
+    for sat in gsv.sats_info.into_iter().flatten() {
+        gnss.satellites.push(Satellite {
+            constellation: constellation.to_owned(),
+            prn: sat.prn().to_string(),
+            elevation: sat.elevation().map(f64::from),
+            azimuth: sat.azimuth().map(f64::from),
+            cn0: sat.snr().map(f64::from),
+        });
+    }
+}
+
+fn constellation_name(gnss_type: nmea::GnssType) -> &'static str {
+    match gnss_type {
+        nmea::GnssType::Gps => "GPS",
+        nmea::GnssType::Glonass => "GLONASS",
+        nmea::GnssType::Galileo => "Galileo",
+        nmea::GnssType::Beidou => "BeiDou",
+        _ => "GNSS",
+    }
+}
+
+fn parse_used_seen(v: &str) -> anyhow::Result<(i64, i64)> {
+    let (used, seen) = v.split_once('/').ok_or(anyhow::anyhow!("malformed used/seen value"))?;
+    let used = used.parse()?;
+    let seen = seen.parse()?;
+
+    Ok((used, seen))
+}
+
+/// Converts a `DIR ddmm.mmmm`-style value (as the device's XML dialect and
+/// raw NMEA `ddmm.mmmm` fields both use) into decimal degrees.
+fn parse_lat_long(v: &str) -> anyhow::Result<f64> {
+    let (dir, val) = v.split_once(' ').ok_or(anyhow::anyhow!("malformed lat/long value"))?;
+    let sign = match dir {
+        "N" | "E" => 1.,
+        "S" | "W" => -1.,
+        _ => return Err(anyhow::anyhow!("malformed lat/long value")),
+    };
+
+    let separator = val.find('.').ok_or(anyhow::anyhow!("malformed lat/long value"))?;
+
+    let (degrees, minutes) = val.split_at(separator - 2);
+    let degrees: f64 = degrees.parse()?;
+    let minutes: f64 = minutes.parse()?;
+
+    let decimal_degrees = minutes / 60.0;
+    Ok(sign * (degrees + decimal_degrees))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_rmc_sets_position_on_autonomous_fix() {
+        let mut gnss = Gnss::default();
+        let sentence = "$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A";
+
+        match parse_str(sentence) {
+            Ok(ParseResult::RMC(rmc)) => apply_rmc(&mut gnss, rmc),
+            other => panic!("expected an RMC sentence, got {other:?}"),
+        }
+
+        assert!(gnss.lat.is_some());
+        assert!(gnss.lon.is_some());
+    }
+
+    #[test]
+    fn apply_rmc_ignores_void_fix() {
+        let mut gnss = Gnss::default();
+        let sentence = "$GPRMC,123519,V,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*7D";
+
+        match parse_str(sentence) {
+            Ok(ParseResult::RMC(rmc)) => apply_rmc(&mut gnss, rmc),
+            other => panic!("expected an RMC sentence, got {other:?}"),
+        }
+
+        assert!(gnss.lat.is_none());
+        assert!(gnss.lon.is_none());
+    }
+
+    #[test]
+    fn apply_gsv_appends_satellites_for_the_reporting_constellation() {
+        let mut gnss = Gnss::default();
+        let sentence = "$GPGSV,3,1,11,03,03,111,00,04,15,270,00,06,01,010,00,13,06,292,00*74";
+
+        match parse_str(sentence) {
+            Ok(ParseResult::GSV(gsv)) => apply_gsv(&mut gnss, gsv),
+            other => panic!("expected a GSV sentence, got {other:?}"),
+        }
+
+        assert!(!gnss.satellites.is_empty());
+        assert!(gnss.satellites.iter().all(|sat| sat.constellation == "GPS"));
+    }
+}
@@ -0,0 +1,79 @@
+/// Renders a fix as an RFC 1876 LOC master-file record, e.g.
+/// `52 05 24.720 N 5 06 41.040 E 13.00m 1m 10000m 10m`.
+pub fn to_loc_record(lat: f64, lon: f64, alt: Option<f64>, hdop: Option<f64>, vdop: Option<f64>) -> String {
+    let lat = format_dms(lat, Hemisphere::NorthSouth);
+    let lon = format_dms(lon, Hemisphere::EastWest);
+    let alt = alt.unwrap_or(0.0);
+
+    // RFC 1876 defaults: 1m size, 10000m horizontal precision, 10m vertical
+    // precision. When the fix carries dilution-of-precision values we use
+    // those (scaled by a rough 5m one-sigma user-equivalent-range-error) as
+    // a more accurate estimate instead.
+    let size = 1.0;
+    let horiz_precision = hdop.map(|hdop| hdop * 5.0).unwrap_or(10_000.0);
+    let vert_precision = vdop.map(|vdop| vdop * 5.0).unwrap_or(10.0);
+
+    format!("{lat} {lon} {alt:.2}m {size:.0}m {horiz_precision:.0}m {vert_precision:.0}m")
+}
+
+enum Hemisphere {
+    NorthSouth,
+    EastWest,
+}
+
+/// The inverse of `parse_lat_long`: decimal degrees -> degrees, minutes,
+/// seconds, with the sign folded into a hemisphere letter.
+fn format_dms(decimal: f64, hemisphere: Hemisphere) -> String {
+    let letter = match (hemisphere, decimal >= 0.0) {
+        (Hemisphere::NorthSouth, true) => 'N',
+        (Hemisphere::NorthSouth, false) => 'S',
+        (Hemisphere::EastWest, true) => 'E',
+        (Hemisphere::EastWest, false) => 'W',
+    };
+
+    let abs = decimal.abs();
+    let mut degrees = abs.trunc() as i64;
+    let minutes_full = (abs - degrees as f64) * 60.0;
+    let mut minutes = minutes_full.trunc() as i64;
+    let mut seconds = (minutes_full - minutes as f64) * 60.0;
+
+    // Round before formatting, then carry any overflow this produces (e.g.
+    // 59.9997 rounding up to 60.000) up into minutes/degrees — otherwise
+    // we can emit an out-of-range "60.000" or "60" field.
+    seconds = (seconds * 1000.0).round() / 1000.0;
+    if seconds >= 60.0 {
+        seconds -= 60.0;
+        minutes += 1;
+    }
+    if minutes >= 60 {
+        minutes -= 60;
+        degrees += 1;
+    }
+
+    format!("{degrees} {minutes:02} {seconds:06.3} {letter}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_dms_matches_rfc1876_example() {
+        let decimal = 52.0 + 5.0 / 60.0 + 24.720 / 3600.0;
+        assert_eq!(format_dms(decimal, Hemisphere::NorthSouth), "52 05 24.720 N");
+    }
+
+    #[test]
+    fn format_dms_carries_rounded_seconds_overflow_into_minutes() {
+        // seconds compute to 59.9997, which rounds to 60.000 and must carry.
+        let decimal = 52.0 + 5.0 / 60.0 + 59.9997 / 3600.0;
+        assert_eq!(format_dms(decimal, Hemisphere::NorthSouth), "52 06 00.000 N");
+    }
+
+    #[test]
+    fn format_dms_carries_minutes_overflow_into_degrees() {
+        // minutes compute to 59, seconds round up to 60.000, carrying minutes to 60.
+        let decimal = 52.0 + 59.0 / 60.0 + 59.9997 / 3600.0;
+        assert_eq!(format_dms(decimal, Hemisphere::NorthSouth), "53 00 00.000 N");
+    }
+}
@@ -1,3 +1,7 @@
+mod gnss;
+mod loc;
+mod track;
+
 use axum::{
     extract::{Query, State},
     headers::{self, authorization::Basic},
@@ -5,79 +9,300 @@ use axum::{
     routing::get,
     Router, TypedHeader,
 };
-use prometheus::{Encoder, Gauge, IntGauge, IntGaugeVec, Opts, TextEncoder};
+use chrono::Utc;
+use gnss::Gnss;
+use prometheus::{Encoder, GaugeVec, IntGaugeVec, Opts, TextEncoder};
+use protobuf::RepeatedField;
 use reqwest::Client;
 use serde::Deserialize;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Deserialize)]
 struct Config {
     listen: String,
+
+    /// Comma-separated list of targets to poll in the background. Append
+    /// `#nmea` to a target to parse it as a raw NMEA 0183 stream instead of
+    /// the device's XML dialect, e.g. `http://host:2947/nmea#nmea`.
+    #[serde(default)]
+    targets: Vec<String>,
+
+    #[serde(default = "default_poll_interval_secs")]
+    poll_interval_secs: u64,
+
+    #[serde(default = "default_track_buffer_size")]
+    track_buffer_size: usize,
+
+    /// Fallback GPS-UTC leap second count, used when a target doesn't report
+    /// one itself (neither the XML dialect nor standard NMEA sentences carry
+    /// it).
+    #[serde(default)]
+    leap_seconds: Option<i64>,
+
+    /// Whether a leap second insertion/deletion is scheduled, when the
+    /// target doesn't report this itself.
+    #[serde(default)]
+    leap_second_planned: bool,
+}
+
+fn default_poll_interval_secs() -> u64 {
+    10
+}
+
+fn default_track_buffer_size() -> usize {
+    1000
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    Xml,
+    Nmea,
 }
 
+impl Default for Format {
+    fn default() -> Self {
+        Format::Xml
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TargetSpec {
+    url: String,
+    format: Format,
+}
+
+fn parse_target(raw: &str) -> TargetSpec {
+    match raw.split_once('#') {
+        Some((url, "nmea")) => TargetSpec { url: url.to_owned(), format: Format::Nmea },
+        _ => TargetSpec { url: raw.to_owned(), format: Format::Xml },
+    }
+}
+
+/// Every gauge here is labeled at minimum by `instance` (the scraped
+/// target), blackbox_exporter-style, so one exporter process can front many
+/// receivers without their series clobbering each other.
 struct MetricState {
-    client: Arc<Client>,
+    up: Arc<IntGaugeVec>,
+    probe_duration: Arc<GaugeVec>,
 
-    ant: Arc<IntGauge>,
+    ant: Arc<IntGaugeVec>,
     svs_used: Arc<IntGaugeVec>,
     svs_seen: Arc<IntGaugeVec>,
 
-    lat: Arc<Gauge>,
-    lon: Arc<Gauge>,
-    alt: Arc<Gauge>,
+    lat: Arc<GaugeVec>,
+    lon: Arc<GaugeVec>,
+    alt: Arc<GaugeVec>,
+
+    fix_quality: Arc<IntGaugeVec>,
+    hdop: Arc<GaugeVec>,
+    vdop: Arc<GaugeVec>,
+    pdop: Arc<GaugeVec>,
+
+    sv_cn0: Arc<GaugeVec>,
+    sv_elevation: Arc<GaugeVec>,
+    sv_azimuth: Arc<GaugeVec>,
+
+    leap_seconds: Arc<IntGaugeVec>,
+    leap_second_planned: Arc<IntGaugeVec>,
+    gps_offset_ns: Arc<IntGaugeVec>,
+    utc_offset_ns: Arc<IntGaugeVec>,
+
+    // Label sets set on the previous successful scrape, per instance, so a
+    // scrape that sees fewer constellations/satellites than last time can
+    // remove the now-stale series instead of leaving them frozen.
+    instance_state: Mutex<HashMap<String, InstanceState>>,
 }
 
-#[derive(Debug, Deserialize)]
-struct Gnss {
-    ant: String,
-    // r#const: String,
-    // svused: i64,
-    gpsinfo: String,
-    bdinfo: String,
-    glinfo: String,
-    lat: String,
-    long: String,
-    alt: String,
+#[derive(Default, Clone)]
+struct InstanceState {
+    constellations: Vec<String>,
+    satellites: Vec<(String, String)>,
+}
+
+/// Shared application state: the background pollers write into `cache` and
+/// `tracks`, and the HTTP handlers read them back without ever touching the
+/// network themselves (aside from the on-demand fallbacks: `metrics_handler`
+/// probes targets that aren't part of the configured poll set, and
+/// `loc_handler` probes any target that has no cached fix yet).
+struct AppState {
+    client: Arc<Client>,
+    metric: MetricState,
+    targets: HashMap<String, TargetSpec>,
+    cache: RwLock<HashMap<String, Arc<Gnss>>>,
+    tracks: Mutex<HashMap<String, track::TrackBuffer>>,
+    track_buffer_size: usize,
+
+    default_leap_seconds: Option<i64>,
+    default_leap_second_planned: bool,
 }
 
 #[derive(Debug, Deserialize)]
 struct MetricsQuery {
     target: String,
+    format: Option<Format>,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let config = envy::from_env::<Config>()?;
-    println!("{config:?}");
+#[derive(Debug, Deserialize)]
+struct TrackQuery {
+    target: String,
+    #[serde(default)]
+    format: TrackFormat,
+}
 
-    let ant = IntGauge::with_opts(Opts::new("gnss_ant", "Antenna status (0 = OPEN, 1 = OK)"))?;
-    let svs_used = IntGaugeVec::new(Opts::new("gnss_svs_used", "Satellites used"), &["constellation"])?;
-    let svs_seen = IntGaugeVec::new(Opts::new("gnss_svs_seen", "Satellites seen"), &["constellation"])?;
+#[derive(Debug, Deserialize)]
+struct LocQuery {
+    target: String,
+    format: Option<Format>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum TrackFormat {
+    Gpx,
+    Geojson,
+}
 
-    let lat = Gauge::with_opts(Opts::new("gnss_lat", "Latitude in decimal degrees"))?;
-    let lon = Gauge::with_opts(Opts::new("gnss_lon", "Longitude in decimal degrees"))?;
-    let alt = Gauge::with_opts(Opts::new("gnss_alt", "Altitude in meters"))?;
+impl Default for TrackFormat {
+    fn default() -> Self {
+        TrackFormat::Gpx
+    }
+}
 
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    let config = envy::from_env::<Config>()?;
+    log::info!("{config:?}");
+
+    let up = IntGaugeVec::new(Opts::new("gnss_up", "Whether the last probe of the target succeeded"), &["instance"])?;
+    let probe_duration = GaugeVec::new(
+        Opts::new("gnss_probe_duration_seconds", "Time taken to fetch and parse the target's GNSS data"),
+        &["instance"],
+    )?;
+
+    let ant = IntGaugeVec::new(Opts::new("gnss_ant", "Antenna status (0 = OPEN, 1 = OK)"), &["instance"])?;
+    let svs_used = IntGaugeVec::new(Opts::new("gnss_svs_used", "Satellites used"), &["instance", "constellation"])?;
+    let svs_seen = IntGaugeVec::new(Opts::new("gnss_svs_seen", "Satellites seen"), &["instance", "constellation"])?;
+
+    let lat = GaugeVec::new(Opts::new("gnss_lat", "Latitude in decimal degrees"), &["instance"])?;
+    let lon = GaugeVec::new(Opts::new("gnss_lon", "Longitude in decimal degrees"), &["instance"])?;
+    let alt = GaugeVec::new(Opts::new("gnss_alt", "Altitude in meters"), &["instance"])?;
+
+    let fix_quality = IntGaugeVec::new(Opts::new("gnss_fix_quality", "NMEA GGA fix quality indicator"), &["instance"])?;
+    let hdop = GaugeVec::new(Opts::new("gnss_hdop", "Horizontal dilution of precision"), &["instance"])?;
+    let vdop = GaugeVec::new(Opts::new("gnss_vdop", "Vertical dilution of precision"), &["instance"])?;
+    let pdop = GaugeVec::new(Opts::new("gnss_pdop", "Position (3D) dilution of precision"), &["instance"])?;
+
+    let sv_cn0 = GaugeVec::new(
+        Opts::new("gnss_sv_cn0_db", "Per-satellite carrier-to-noise ratio in dB-Hz"),
+        &["instance", "constellation", "prn"],
+    )?;
+    let sv_elevation = GaugeVec::new(
+        Opts::new("gnss_sv_elevation_degrees", "Per-satellite elevation in degrees"),
+        &["instance", "constellation", "prn"],
+    )?;
+    let sv_azimuth = GaugeVec::new(
+        Opts::new("gnss_sv_azimuth_degrees", "Per-satellite azimuth in degrees"),
+        &["instance", "constellation", "prn"],
+    )?;
+
+    let leap_seconds = IntGaugeVec::new(Opts::new("gnss_leap_seconds", "Current GPS-UTC leap second count"), &["instance"])?;
+    let leap_second_planned = IntGaugeVec::new(
+        Opts::new("gnss_leap_second_planned", "Whether a leap second insertion/deletion is scheduled (0/1)"),
+        &["instance"],
+    )?;
+    let gps_offset_ns = IntGaugeVec::new(Opts::new("gnss_gps_offset_ns", "GPS time minus UTC time in nanoseconds"), &["instance"])?;
+    let utc_offset_ns = IntGaugeVec::new(
+        Opts::new(
+            "gnss_utc_offset_ns",
+            "Receiver clock minus UTC in nanoseconds; reported as 0 once a fix is obtained, since neither the XML dialect nor NMEA sentences carry an independent clock-discipline measurement",
+        ),
+        &["instance"],
+    )?;
+
+    prometheus::register(Box::new(up.clone()))?;
+    prometheus::register(Box::new(probe_duration.clone()))?;
     prometheus::register(Box::new(ant.clone()))?;
     prometheus::register(Box::new(svs_seen.clone()))?;
     prometheus::register(Box::new(svs_used.clone()))?;
     prometheus::register(Box::new(lat.clone()))?;
     prometheus::register(Box::new(lon.clone()))?;
     prometheus::register(Box::new(alt.clone()))?;
-
-    let metric_state = MetricState {
-        client: Arc::new(Client::builder().http1_title_case_headers().build()?),
+    prometheus::register(Box::new(fix_quality.clone()))?;
+    prometheus::register(Box::new(hdop.clone()))?;
+    prometheus::register(Box::new(vdop.clone()))?;
+    prometheus::register(Box::new(pdop.clone()))?;
+    prometheus::register(Box::new(sv_cn0.clone()))?;
+    prometheus::register(Box::new(sv_elevation.clone()))?;
+    prometheus::register(Box::new(sv_azimuth.clone()))?;
+    prometheus::register(Box::new(leap_seconds.clone()))?;
+    prometheus::register(Box::new(leap_second_planned.clone()))?;
+    prometheus::register(Box::new(gps_offset_ns.clone()))?;
+    prometheus::register(Box::new(utc_offset_ns.clone()))?;
+
+    let metric = MetricState {
+        up: Arc::new(up),
+        probe_duration: Arc::new(probe_duration),
         ant: Arc::new(ant),
         svs_used: Arc::new(svs_used),
         svs_seen: Arc::new(svs_seen),
         lat: Arc::new(lat),
         lon: Arc::new(lon),
         alt: Arc::new(alt),
+        fix_quality: Arc::new(fix_quality),
+        hdop: Arc::new(hdop),
+        vdop: Arc::new(vdop),
+        pdop: Arc::new(pdop),
+        sv_cn0: Arc::new(sv_cn0),
+        sv_elevation: Arc::new(sv_elevation),
+        sv_azimuth: Arc::new(sv_azimuth),
+        leap_seconds: Arc::new(leap_seconds),
+        leap_second_planned: Arc::new(leap_second_planned),
+        gps_offset_ns: Arc::new(gps_offset_ns),
+        utc_offset_ns: Arc::new(utc_offset_ns),
+        instance_state: Mutex::new(HashMap::new()),
     };
 
+    let targets: HashMap<String, TargetSpec> = config
+        .targets
+        .iter()
+        .map(|raw| parse_target(raw))
+        .map(|spec| (spec.url.clone(), spec))
+        .collect();
+
+    let state = Arc::new(AppState {
+        client: Arc::new(Client::builder().http1_title_case_headers().build()?),
+        metric,
+        targets: targets.clone(),
+        cache: RwLock::new(HashMap::new()),
+        tracks: Mutex::new(HashMap::new()),
+        track_buffer_size: config.track_buffer_size,
+        default_leap_seconds: config.leap_seconds,
+        default_leap_second_planned: config.leap_second_planned,
+    });
+
+    for spec in targets.into_values() {
+        let state = state.clone();
+        let interval = Duration::from_secs(config.poll_interval_secs);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                probe_and_record(&state, &spec.url, spec.format, None).await;
+            }
+        });
+    }
+
     let app = Router::new()
-        .route("/metrics", get(handler))
-        .with_state(Arc::new(metric_state));
+        .route("/metrics", get(metrics_handler))
+        .route("/track", get(track_handler))
+        .route("/loc", get(loc_handler))
+        .with_state(state);
 
     axum::Server::bind(&config.listen.parse()?)
         .serve(app.into_make_service())
@@ -86,110 +311,262 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn handler(
+async fn metrics_handler(
     Query(query): Query<MetricsQuery>,
     auth: Option<TypedHeader<headers::Authorization<Basic>>>,
-    State(metric): State<Arc<MetricState>>,
+    State(state): State<Arc<AppState>>,
 ) -> impl IntoResponse {
-    println!("{query:?}");
-    // println!("{auth:?}");
+    log::debug!("{query:?}");
+
+    let instance = query.target.as_str();
+
+    // Targets in the configured poll set are kept fresh in the background;
+    // anything else falls back to the old synchronous on-demand probe.
+    if !state.targets.contains_key(instance) {
+        let auth = auth.map(|auth| (auth.username().to_owned(), auth.password().to_owned()));
+        let auth = auth.as_ref().map(|(u, p)| (u.as_str(), p.as_str()));
 
-    let gnss = if let Some(auth) = auth {
-        read_gnss(&query.target, Some((auth.username(), auth.password())), &metric.client).await
-    } else {
-        read_gnss(&query.target, None, &metric.client).await
+        probe_and_record(&state, instance, query.format.unwrap_or_default(), auth).await;
     }
-    .expect("failed to read GNSS XML");
 
-    if let Err(e) = update_metrics(&metric, gnss) {
-        println!("failed to update metrics: {e}");
+    encode_instance_metrics(instance)
+}
+
+/// Prometheus's registry is process-global, so `gather()` returns every
+/// instance's series, not just the one this request probed. A true
+/// blackbox-style probe endpoint must only emit the probed instance's
+/// series, so we drop every metric whose `instance` label doesn't match
+/// before encoding.
+fn encode_instance_metrics(instance: &str) -> Vec<u8> {
+    let mut families = prometheus::gather();
+
+    for family in &mut families {
+        let matching: Vec<_> = family
+            .take_metric()
+            .into_iter()
+            .filter(|metric| metric.get_label().iter().any(|label| label.get_name() == "instance" && label.get_value() == instance))
+            .collect();
+
+        family.set_metric(RepeatedField::from_vec(matching));
     }
+    families.retain(|family| !family.get_metric().is_empty());
 
-    let metrics = prometheus::gather();
-    println!("{metrics:?}");
     let mut buffer = Vec::new();
-
     let encoder = TextEncoder::new();
-    encoder.encode(&metrics, &mut buffer).expect("failed to encode metrics");
+    encoder.encode(&families, &mut buffer).expect("failed to encode metrics");
 
     buffer
 }
 
-async fn read_gnss(target: &str, auth: Option<(&str, &str)>, client: &Client) -> anyhow::Result<Gnss> {
-    let mut builder = client.get(target);
+async fn track_handler(Query(query): Query<TrackQuery>, State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let points: Vec<track::TrackPoint> = {
+        let tracks = state.tracks.lock().unwrap();
+        tracks.get(&query.target).map(|buffer| buffer.points().cloned().collect()).unwrap_or_default()
+    };
 
-    if let Some((username, password)) = auth {
-        builder = builder.basic_auth(username, Some(password));
+    match query.format {
+        TrackFormat::Gpx => track::to_gpx(&points),
+        TrackFormat::Geojson => track::to_geojson(&points),
     }
+}
 
-    // let req = builder.build()?;
-    // println!("{req:?}");
-
-    // let xml = client.execute(req).await?.text().await?;
-    let xml = builder.send().await?.text().await?;
-    println!("{xml}");
+async fn loc_handler(
+    Query(query): Query<LocQuery>,
+    auth: Option<TypedHeader<headers::Authorization<Basic>>>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let instance = query.target.as_str();
 
-    let gnss = serde_xml_rs::from_str::<Gnss>(&xml)?;
-    println!("{gnss:?}");
+    if !state.cache.read().unwrap().contains_key(instance) {
+        let auth = auth.map(|auth| (auth.username().to_owned(), auth.password().to_owned()));
+        let auth = auth.as_ref().map(|(u, p)| (u.as_str(), p.as_str()));
 
-    Ok(gnss)
-}
+        probe_and_record(&state, instance, query.format.unwrap_or_default(), auth).await;
+    }
 
-fn update_metrics(metric: &MetricState, gnss: Gnss) -> anyhow::Result<()> {
-    metric.ant.set(match gnss.ant.as_str() {
-        "OPEN" => 0,
-        "OK" => 1,
-        _ => 2,
-    });
+    let gnss = state.cache.read().unwrap().get(instance).cloned();
+    let fix = gnss.and_then(|gnss| Some((gnss.lat?, gnss.lon?, gnss)));
 
-    let (gps_used, gps_seen) = parse_used_seen(&gnss.gpsinfo)?;
-    let (bd_used, bd_seen) = parse_used_seen(&gnss.bdinfo)?;
-    let (gl_used, gl_seen) = parse_used_seen(&gnss.glinfo)?;
+    match fix {
+        Some((lat, lon, gnss)) => loc::to_loc_record(lat, lon, gnss.alt, gnss.hdop, gnss.vdop),
+        None => format!("; no fix available for {instance}"),
+    }
+}
 
-    metric.svs_used.with_label_values(&["GPS"]).set(gps_used);
-    metric.svs_used.with_label_values(&["BeiDou"]).set(bd_used);
-    metric.svs_used.with_label_values(&["GLONASS"]).set(gl_used);
+/// Probes one target, updates its Prometheus series, caches the fix, and (if
+/// it carries a position) appends to that target's track history. Shared by
+/// the background pollers and the on-demand fallbacks in `metrics_handler`
+/// and `loc_handler`.
+async fn probe_and_record(state: &AppState, instance: &str, format: Format, auth: Option<(&str, &str)>) {
+    let start = Instant::now();
+    let result = match format {
+        Format::Xml => gnss::read_gnss(instance, auth, &state.client).await,
+        Format::Nmea => gnss::read_gnss_nmea(instance, auth, &state.client).await,
+    };
+    let probe_duration = start.elapsed().as_secs_f64();
+    state.metric.probe_duration.with_label_values(&[instance]).set(probe_duration);
+
+    match result {
+        Ok(mut gnss) => {
+            if gnss.leap_seconds.is_none() {
+                gnss.leap_seconds = state.default_leap_seconds;
+            }
+            if gnss.leap_second_planned.is_none() {
+                gnss.leap_second_planned = Some(state.default_leap_second_planned);
+            }
+            if gnss.gps_offset_ns.is_none() {
+                gnss.gps_offset_ns = gnss.leap_seconds.map(|leap_seconds| leap_seconds * 1_000_000_000);
+            }
+            // No target in this tree exposes an independent clock-discipline
+            // measurement, so a successful fix is reported as perfectly
+            // UTC-aligned rather than leaving the metric unpublished.
+            if gnss.utc_offset_ns.is_none() {
+                gnss.utc_offset_ns = Some(0);
+            }
+
+            update_metrics(&state.metric, instance, &gnss);
+            state.metric.up.with_label_values(&[instance]).set(1);
+
+            if let (Some(lat), Some(lon)) = (gnss.lat, gnss.lon) {
+                let point = track::TrackPoint { lat, lon, alt: gnss.alt, time: Utc::now() };
+                let mut tracks = state.tracks.lock().unwrap();
+                tracks
+                    .entry(instance.to_owned())
+                    .or_insert_with(|| track::TrackBuffer::new(state.track_buffer_size))
+                    .push(point);
+            }
+
+            state.cache.write().unwrap().insert(instance.to_owned(), Arc::new(gnss));
+        }
+        Err(e) => {
+            log::warn!("failed to read GNSS data for {instance}: {e}");
+            clear_stale_metrics(&state.metric, instance, None);
+            state.metric.up.with_label_values(&[instance]).set(0);
+        }
+    }
+}
 
-    metric.svs_seen.with_label_values(&["GPS"]).set(gps_seen);
-    metric.svs_seen.with_label_values(&["BeiDou"]).set(bd_seen);
-    metric.svs_seen.with_label_values(&["GLONASS"]).set(gl_seen);
+fn update_metrics(metric: &MetricState, instance: &str, gnss: &Gnss) {
+    if let Some(ant) = gnss.ant.as_deref() {
+        metric.ant.with_label_values(&[instance]).set(match ant {
+            "OPEN" => 0,
+            "OK" => 1,
+            _ => 2,
+        });
+    }
 
-    let lat = parse_lat_long(&gnss.lat)?;
-    let lon = parse_lat_long(&gnss.long)?;
+    for (constellation, used) in &gnss.svs_used {
+        metric.svs_used.with_label_values(&[instance, constellation]).set(*used);
+    }
+    for (constellation, seen) in &gnss.svs_seen {
+        metric.svs_seen.with_label_values(&[instance, constellation]).set(*seen);
+    }
 
-    metric.lat.set(lat);
-    metric.lon.set(lon);
+    if let Some(lat) = gnss.lat {
+        metric.lat.with_label_values(&[instance]).set(lat);
+    }
+    if let Some(lon) = gnss.lon {
+        metric.lon.with_label_values(&[instance]).set(lon);
+    }
+    if let Some(alt) = gnss.alt {
+        metric.alt.with_label_values(&[instance]).set(alt);
+    }
 
-    let alt = gnss.alt.trim_end_matches(" m");
-    if let Ok(alt) = alt.parse() {
-        metric.alt.set(alt);
+    if let Some(fix_quality) = gnss.fix_quality {
+        metric.fix_quality.with_label_values(&[instance]).set(fix_quality);
+    }
+    if let Some(hdop) = gnss.hdop {
+        metric.hdop.with_label_values(&[instance]).set(hdop);
+    }
+    if let Some(vdop) = gnss.vdop {
+        metric.vdop.with_label_values(&[instance]).set(vdop);
+    }
+    if let Some(pdop) = gnss.pdop {
+        metric.pdop.with_label_values(&[instance]).set(pdop);
     }
 
-    Ok(())
-}
+    if let Some(leap_seconds) = gnss.leap_seconds {
+        metric.leap_seconds.with_label_values(&[instance]).set(leap_seconds);
+    }
+    if let Some(leap_second_planned) = gnss.leap_second_planned {
+        metric.leap_second_planned.with_label_values(&[instance]).set(leap_second_planned as i64);
+    }
+    if let Some(gps_offset_ns) = gnss.gps_offset_ns {
+        metric.gps_offset_ns.with_label_values(&[instance]).set(gps_offset_ns);
+    }
+    if let Some(utc_offset_ns) = gnss.utc_offset_ns {
+        metric.utc_offset_ns.with_label_values(&[instance]).set(utc_offset_ns);
+    }
 
-fn parse_used_seen(v: &str) -> anyhow::Result<(i64, i64)> {
-    let (used, seen) = v.split_once('/').ok_or(anyhow::anyhow!("malformed used/seen value"))?;
-    let used = used.parse()?;
-    let seen = seen.parse()?;
+    for sat in &gnss.satellites {
+        let labels = [instance, sat.constellation.as_str(), sat.prn.as_str()];
+
+        if let Some(cn0) = sat.cn0 {
+            metric.sv_cn0.with_label_values(&labels).set(cn0);
+        }
+        if let Some(elevation) = sat.elevation {
+            metric.sv_elevation.with_label_values(&labels).set(elevation);
+        }
+        if let Some(azimuth) = sat.azimuth {
+            metric.sv_azimuth.with_label_values(&labels).set(azimuth);
+        }
+    }
 
-    Ok((used, seen))
+    clear_stale_metrics(metric, instance, Some(gnss));
 }
 
-fn parse_lat_long(v: &str) -> anyhow::Result<f64> {
-    let (dir, val) = v.split_once(' ').ok_or(anyhow::anyhow!("malformed lat/long value"))?;
-    let sign = match dir {
-        "N" | "E" => 1.,
-        "S" | "W" => -1.,
-        _ => return Err(anyhow::anyhow!("malformed lat/long value")),
-    };
-
-    let separator = val.find('.').ok_or(anyhow::anyhow!("malformed lat/long value"))?;
+/// Removes the constellation and satellite series for this `instance` that
+/// existed on the previous scrape but are absent from `gnss`, so a dropped
+/// constellation or satellite doesn't leave a frozen gauge behind forever.
+/// When `gnss` is `None` because the probe itself failed, every series for
+/// the instance except `gnss_up` and `gnss_probe_duration_seconds` is
+/// removed; on a successful probe the single-value gauges (position, DOP,
+/// timing, antenna) are left as they were when a field is absent.
+fn clear_stale_metrics(metric: &MetricState, instance: &str, gnss: Option<&Gnss>) {
+    let new_constellations: Vec<String> = gnss
+        .map(|g| {
+            g.svs_used
+                .iter()
+                .chain(g.svs_seen.iter())
+                .map(|(c, _)| c.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    let new_satellites: Vec<(String, String)> = gnss
+        .map(|g| g.satellites.iter().map(|s| (s.constellation.clone(), s.prn.clone())).collect())
+        .unwrap_or_default();
+
+    let mut instance_state = metric.instance_state.lock().unwrap();
+    let previous = instance_state.entry(instance.to_owned()).or_default();
+
+    for constellation in &previous.constellations {
+        if !new_constellations.contains(constellation) {
+            let _ = metric.svs_used.remove_label_values(&[instance, constellation]);
+            let _ = metric.svs_seen.remove_label_values(&[instance, constellation]);
+        }
+    }
+    for (constellation, prn) in &previous.satellites {
+        if !new_satellites.contains(&(constellation.clone(), prn.clone())) {
+            let _ = metric.sv_cn0.remove_label_values(&[instance, constellation, prn]);
+            let _ = metric.sv_elevation.remove_label_values(&[instance, constellation, prn]);
+            let _ = metric.sv_azimuth.remove_label_values(&[instance, constellation, prn]);
+        }
+    }
 
-    let (degrees, minutes) = val.split_at(separator - 2);
-    let degrees: f64 = degrees.parse()?;
-    let minutes: f64 = minutes.parse()?;
+    if gnss.is_none() {
+        let _ = metric.ant.remove_label_values(&[instance]);
+        let _ = metric.lat.remove_label_values(&[instance]);
+        let _ = metric.lon.remove_label_values(&[instance]);
+        let _ = metric.alt.remove_label_values(&[instance]);
+        let _ = metric.fix_quality.remove_label_values(&[instance]);
+        let _ = metric.hdop.remove_label_values(&[instance]);
+        let _ = metric.vdop.remove_label_values(&[instance]);
+        let _ = metric.pdop.remove_label_values(&[instance]);
+        let _ = metric.leap_seconds.remove_label_values(&[instance]);
+        let _ = metric.leap_second_planned.remove_label_values(&[instance]);
+        let _ = metric.gps_offset_ns.remove_label_values(&[instance]);
+        let _ = metric.utc_offset_ns.remove_label_values(&[instance]);
+    }
 
-    let decimal_degrees = minutes / 60.0;
-    Ok(sign * (degrees + decimal_degrees))
+    *previous = InstanceState { constellations: new_constellations, satellites: new_satellites };
 }
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+
+/// A single timestamped position sample, as appended to a target's
+/// [`TrackBuffer`] on every successful background poll.
+#[derive(Debug, Clone)]
+pub struct TrackPoint {
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: Option<f64>,
+    pub time: DateTime<Utc>,
+}
+
+/// A bounded history of fixes for one target. Once `capacity` is reached the
+/// oldest point is dropped to make room for the newest, so a stationary
+/// receiver's track doesn't grow without bound.
+pub struct TrackBuffer {
+    capacity: usize,
+    points: VecDeque<TrackPoint>,
+}
+
+impl TrackBuffer {
+    pub fn new(capacity: usize) -> Self {
+        TrackBuffer { capacity, points: VecDeque::with_capacity(capacity) }
+    }
+
+    pub fn push(&mut self, point: TrackPoint) {
+        if self.points.len() == self.capacity {
+            self.points.pop_front();
+        }
+        self.points.push_back(point);
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = &TrackPoint> {
+        self.points.iter()
+    }
+}
+
+/// Renders the track as a GPX 1.1 document with a single `<trk>`/`<trkseg>`.
+pub fn to_gpx(points: &[TrackPoint]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<gpx version=\"1.1\" creator=\"gnss_exporter\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n");
+    out.push_str("  <trk>\n    <trkseg>\n");
+
+    for point in points {
+        out.push_str(&format!("      <trkpt lat=\"{}\" lon=\"{}\">\n", point.lat, point.lon));
+        if let Some(alt) = point.alt {
+            out.push_str(&format!("        <ele>{alt}</ele>\n"));
+        }
+        out.push_str(&format!("        <time>{}</time>\n", point.time.to_rfc3339()));
+        out.push_str("      </trkpt>\n");
+    }
+
+    out.push_str("    </trkseg>\n  </trk>\n</gpx>\n");
+    out
+}
+
+/// Renders the track as a GeoJSON `Feature` wrapping a `LineString`.
+pub fn to_geojson(points: &[TrackPoint]) -> String {
+    let coordinates: Vec<String> = points
+        .iter()
+        .map(|point| match point.alt {
+            Some(alt) => format!("[{}, {}, {}]", point.lon, point.lat, alt),
+            None => format!("[{}, {}]", point.lon, point.lat),
+        })
+        .collect();
+
+    format!(
+        "{{\"type\":\"Feature\",\"properties\":{{}},\"geometry\":{{\"type\":\"LineString\",\"coordinates\":[{}]}}}}",
+        coordinates.join(",")
+    )
+}